@@ -1,7 +1,8 @@
-use std::ffi::{CString};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
 use std::sync::Mutex;
 
-use log::{Record, Metadata};
+use log::{Record, Metadata, LevelFilter};
 use once_cell::sync::Lazy;
 
 use crate::status::{rascal_status_t, catch_unwind};
@@ -35,50 +36,188 @@ pub const RASCAL_LOG_LEVEL_TRACE: i32 = 5;
 /// `RASCAL_LOG_LEVEL_WARN` `RASCAL_LOG_LEVEL_INFO`, `RASCAL_LOG_LEVEL_DEBUG`,
 /// or `RASCAL_LOG_LEVEL_TRACE`. The second argument is a NULL-terminated string
 /// containing the message associated with the log event.
+///
+/// This callback flattens the `target` and `message` of the underlying
+/// `log::Record` into a single string; use
+/// [`rascal_logging_callback_v2_t`]/[`rascal_set_logging_callback_v2`] if you
+/// need them separately, or need structured filtering by target.
 #[allow(non_camel_case_types)]
 pub type rascal_logging_callback_t = Option<unsafe extern fn(level: i32, message: *const std::os::raw::c_char)>;
 
+/// Callback function type for the structured rascaline logging system. Such
+/// functions are called when a log event is emitted in the code, with the
+/// different fields of the underlying `log::Record` passed separately
+/// instead of being flattened into a single message.
+///
+/// The first argument is the log level, see [`rascal_logging_callback_t`].
+/// `target` and `message` are NULL-terminated strings giving the log target
+/// (usually the module the event was emitted from) and the formatted log
+/// message. `module_path` and `file` are NULL-terminated strings when this
+/// information is available on the record, and NULL otherwise. `line` is the
+/// source line the event was emitted from, or `-1` when not available.
+#[allow(non_camel_case_types)]
+pub type rascal_logging_callback_v2_t = Option<unsafe extern fn(
+    level: i32,
+    target: *const std::os::raw::c_char,
+    message: *const std::os::raw::c_char,
+    module_path: *const std::os::raw::c_char,
+    file: *const std::os::raw::c_char,
+    line: i32,
+)>;
+
 static GLOBAL_CALLBACK: Lazy<Mutex<rascal_logging_callback_t>> = Lazy::new(|| Mutex::new(None));
+static GLOBAL_CALLBACK_V2: Lazy<Mutex<rascal_logging_callback_v2_t>> = Lazy::new(|| Mutex::new(None));
+
+/// Per-target maximum log level, set through
+/// `rascal_set_log_level_for_target`. This lets bindings toggle e.g. the
+/// `"neighbor list"` `time_graph` span independently from the rest of the
+/// calculator logs, without recompiling.
+static TARGET_MAX_LEVELS: Lazy<Mutex<HashMap<String, LevelFilter>>> = Lazy::new(|| Mutex::new(HashMap::new()));
 
 /// Implementation of `log::Log` that forward all log messages to the global
-/// `rascal_logging_callback_t`.
+/// `rascal_logging_callback_t`/`rascal_logging_callback_v2_t`, whichever was
+/// last registered.
 struct RascalLogger;
 
+/// Default maximum log level for this build profile, before taking any
+/// per-target override into account.
+fn default_max_level() -> LevelFilter {
+    if cfg!(debug_assertions) {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Info
+    }
+}
+
+/// Compute the global `log::max_level` ceiling: `default_max_level` raised to
+/// cover every per-target level in `target_levels`.
+///
+/// The `log` crate gates `trace!()`/`debug!()` call sites on this global
+/// ceiling *before* `RascalLogger::enabled` is ever consulted, so a
+/// per-target level more permissive than the default must also raise this
+/// ceiling, or the corresponding call sites get compiled out and
+/// `rascal_set_log_level_for_target` can never actually surface them.
+fn global_max_level(target_levels: &HashMap<String, LevelFilter>) -> LevelFilter {
+    return target_levels.values().copied().fold(default_max_level(), LevelFilter::max);
+}
+
+/// Install `RascalLogger` as the global logger and set the maximum log
+/// level, shared by both `rascal_set_logging_callback` and
+/// `rascal_set_logging_callback_v2`.
+unsafe fn install_logger() {
+    // we allow multiple sets of logger, therefore the result will be ignored
+    let _ = log::set_boxed_logger(Box::new(RascalLogger));
+
+    let target_levels = TARGET_MAX_LEVELS.lock().expect("mutex was poisoned");
+    log::set_max_level(global_max_level(&target_levels));
+}
+
 /// Set the given ``callback`` function as the global logging callback. This
 /// function will be called on all log events. If a logging callback was already
-/// set, it is replaced by the new one.
+/// set (either with this function or with `rascal_set_logging_callback_v2`),
+/// it is replaced by the new one.
 #[no_mangle]
 pub unsafe extern fn rascal_set_logging_callback(callback: rascal_logging_callback_t) -> rascal_status_t {
     catch_unwind(|| {
         *GLOBAL_CALLBACK.lock().expect("mutex was poisoned") = callback;
-        // we allow multiple sets of logger, therefore the result will be ignored
-        let _ = log::set_boxed_logger(Box::new(RascalLogger));
+        // a v2 callback takes priority in `RascalLogger::log`, remove it so
+        // that this callback replaces it
+        *GLOBAL_CALLBACK_V2.lock().expect("mutex was poisoned") = None;
+        install_logger();
 
-        if cfg!(debug_assertions) {
-            log::set_max_level(log::LevelFilter::Debug);
-        } else {
-            log::set_max_level(log::LevelFilter::Info);
-        }
+        Ok(())
+    })
+}
+
+/// Set the given ``callback`` function as the global structured logging
+/// callback. This function will be called on all log events, receiving the
+/// `target`, `message`, `module_path`, `file` and `line` of the event
+/// separately instead of a single flattened string. If a logging callback
+/// was already set (either with this function or with
+/// `rascal_set_logging_callback`), it is replaced by the new one.
+#[no_mangle]
+pub unsafe extern fn rascal_set_logging_callback_v2(callback: rascal_logging_callback_v2_t) -> rascal_status_t {
+    catch_unwind(|| {
+        *GLOBAL_CALLBACK_V2.lock().expect("mutex was poisoned") = callback;
+        install_logger();
 
         Ok(())
     })
 }
 
+/// Set the maximum log level for events coming from the given NULL-terminated
+/// `target` string (usually a module path, e.g. `"neighbor list"` for the
+/// `time_graph` span around `CrappyNeighborsList`), independently of the
+/// global log level set by `rascal_set_logging_callback`/
+/// `rascal_set_logging_callback_v2`. This allows selectively enabling e.g.
+/// `RASCAL_LOG_LEVEL_TRACE` for a single target without recompiling.
+#[no_mangle]
+pub unsafe extern fn rascal_set_log_level_for_target(target: *const std::os::raw::c_char, level: i32) -> rascal_status_t {
+    catch_unwind(|| {
+        let target = CStr::from_ptr(target).to_str().expect("target is not valid UTF8").to_owned();
+        let level = match level {
+            RASCAL_LOG_LEVEL_ERROR => LevelFilter::Error,
+            RASCAL_LOG_LEVEL_WARN => LevelFilter::Warn,
+            RASCAL_LOG_LEVEL_INFO => LevelFilter::Info,
+            RASCAL_LOG_LEVEL_DEBUG => LevelFilter::Debug,
+            RASCAL_LOG_LEVEL_TRACE => LevelFilter::Trace,
+            _ => LevelFilter::Off,
+        };
+
+        let mut target_levels = TARGET_MAX_LEVELS.lock().expect("mutex was poisoned");
+        target_levels.insert(target, level);
+
+        // a more permissive per-target level must also raise the global
+        // `log::max_level` ceiling, or `enabled` below never gets a chance
+        // to run for the corresponding call sites
+        log::set_max_level(global_max_level(&target_levels));
+
+        Ok(())
+    })
+}
 
 impl log::Log for RascalLogger {
-    fn enabled(&self, _: &Metadata) -> bool {
-       return true;
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let target_levels = TARGET_MAX_LEVELS.lock().expect("mutex was poisoned");
+        let max_level = target_levels.get(metadata.target())
+            .copied()
+            .unwrap_or_else(default_max_level);
+
+        return metadata.level() <= max_level;
     }
 
     fn log(&self, record: &Record) {
-        if self.enabled(record.metadata()) {
-            let message = format!("{} -- {}", record.target(), record.args());
-            let message_cstr = CString::new(message).unwrap();
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let v2_callback = *GLOBAL_CALLBACK_V2.lock().expect("mutex was poisoned");
+        if let Some(callback) = v2_callback {
+            let target = CString::new(record.target()).unwrap();
+            let message = CString::new(format!("{}", record.args())).unwrap();
+            let module_path = record.module_path().map(|path| CString::new(path).unwrap());
+            let file = record.file().map(|file| CString::new(file).unwrap());
+            let line = record.line().map_or(-1, |line| line as i32);
+
             unsafe {
-                match *(GLOBAL_CALLBACK.lock().expect("mutex was poisoned")) {
-                    Some(callback) => callback(record.level() as i32, message_cstr.as_ptr()),
-                    None => unreachable!("missing callback but RascalLogger is set as the global logger"),
-                }
+                callback(
+                    record.level() as i32,
+                    target.as_ptr(),
+                    message.as_ptr(),
+                    module_path.as_ref().map_or(std::ptr::null(), |path| path.as_ptr()),
+                    file.as_ref().map_or(std::ptr::null(), |file| file.as_ptr()),
+                    line,
+                );
+            }
+            return;
+        }
+
+        let message = format!("{} -- {}", record.target(), record.args());
+        let message_cstr = CString::new(message).unwrap();
+        unsafe {
+            match *GLOBAL_CALLBACK.lock().expect("mutex was poisoned") {
+                Some(callback) => callback(record.level() as i32, message_cstr.as_ptr()),
+                None => unreachable!("missing callback but RascalLogger is set as the global logger"),
             }
         }
     }
@@ -98,4 +237,63 @@ mod tests {
         assert_eq!(RASCAL_LOG_LEVEL_DEBUG, log::Level::Debug as i32);
         assert_eq!(RASCAL_LOG_LEVEL_TRACE, log::Level::Trace as i32);
     }
+
+    #[test]
+    fn per_target_level_filters_independently_of_global_level() {
+        let logger = RascalLogger;
+
+        let metadata = Metadata::builder()
+            .level(log::Level::Trace)
+            .target("neighbor list")
+            .build();
+        assert!(logger.enabled(&metadata));
+
+        TARGET_MAX_LEVELS.lock().expect("mutex was poisoned")
+            .insert("neighbor list".to_owned(), LevelFilter::Warn);
+        assert!(!logger.enabled(&metadata));
+
+        TARGET_MAX_LEVELS.lock().expect("mutex was poisoned").remove("neighbor list");
+    }
+
+    #[test]
+    fn raising_one_target_does_not_unfilter_unrelated_targets() {
+        let logger = RascalLogger;
+
+        let unrelated = Metadata::builder()
+            .level(log::Level::Trace)
+            .target("some::unrelated::target")
+            .build();
+        // `Trace` is above `default_max_level` (`Debug` in debug builds, `Info`
+        // in release), so this unconfigured target should stay filtered
+        assert!(!logger.enabled(&unrelated));
+
+        TARGET_MAX_LEVELS.lock().expect("mutex was poisoned")
+            .insert("neighbor list".to_owned(), LevelFilter::Trace);
+
+        // raising "neighbor list" must not also raise the ceiling for
+        // `unrelated`, which has no override of its own
+        assert!(!logger.enabled(&unrelated));
+
+        TARGET_MAX_LEVELS.lock().expect("mutex was poisoned").remove("neighbor list");
+    }
+
+    #[test]
+    fn setting_a_target_level_raises_the_global_macro_gate() {
+        // `log::max_level` is the ceiling the `log` crate's macros
+        // (`trace!()`, `debug!()`, ...) are gated on *before* `RascalLogger`
+        // is ever consulted: raising a single target's level to `trace`
+        // must raise this global ceiling too, or the corresponding call
+        // sites get compiled out and never reach `enabled`/`log`.
+        let target = CString::new("some::target").unwrap();
+        unsafe {
+            let _ = rascal_set_log_level_for_target(target.as_ptr(), RASCAL_LOG_LEVEL_TRACE);
+        }
+
+        assert!(log::max_level() >= LevelFilter::Trace);
+
+        TARGET_MAX_LEVELS.lock().expect("mutex was poisoned").remove("some::target");
+        unsafe {
+            install_logger();
+        }
+    }
 }
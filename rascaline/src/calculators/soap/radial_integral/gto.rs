@@ -0,0 +1,404 @@
+use ndarray::{Array2, ArrayViewMut2};
+
+use crate::Error;
+use super::{AtomicGaussianWidth, SoapRadialIntegral};
+
+/// Parameters controlling a [`SoapRadialIntegralGto`]
+#[derive(Debug, Clone)]
+pub struct SoapRadialIntegralGtoParameters {
+    /// Number of radial basis function to use
+    pub max_radial: usize,
+    /// Number of spherical harmonics to compute
+    pub max_angular: usize,
+    /// Width of the atomic Gaussian density, possibly depending on the
+    /// neighbor species, see [`AtomicGaussianWidth`]
+    pub atomic_gaussian_width: AtomicGaussianWidth,
+    /// Cutoff radius, after which the atomic density is zero
+    pub cutoff: f64,
+}
+
+impl SoapRadialIntegralGtoParameters {
+    fn validate(&self) -> Result<(), Error> {
+        if self.max_radial == 0 {
+            return Err(Error::InvalidParameter(
+                "max_radial must be at least 1 for the GTO radial integral".into()
+            ));
+        }
+
+        if self.cutoff <= 0.0 || !self.cutoff.is_finite() {
+            return Err(Error::InvalidParameter(
+                "cutoff must be a positive, finite number".into()
+            ));
+        }
+
+        match &self.atomic_gaussian_width {
+            AtomicGaussianWidth::Constant(width) => {
+                if *width <= 0.0 || !width.is_finite() {
+                    return Err(Error::InvalidParameter(
+                        "atomic Gaussian width must be a positive, finite number".into()
+                    ));
+                }
+            }
+            AtomicGaussianWidth::PerSpecies(widths) => {
+                for (species, width) in widths {
+                    if *width <= 0.0 || !width.is_finite() {
+                        return Err(Error::InvalidParameter(format!(
+                            "atomic Gaussian width for species {} must be a \
+                             positive, finite number", species
+                        )));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// `SoapRadialIntegralGto` computes the SOAP radial integral for a Gaussian
+/// atomic density expanded on a basis of un-normalized Gaussian Type Orbitals
+/// (GTO), orthonormalized through the overlap matrix of the primitive GTOs.
+///
+/// The atomic density width can depend on the neighbor species, see
+/// [`AtomicGaussianWidth`]: the exponential prefactor and the argument of the
+/// modified spherical Bessel function are both recomputed for every call to
+/// [`SoapRadialIntegralGto::compute`], using the width reported for the
+/// specific `species_neighbor` being evaluated.
+pub struct SoapRadialIntegralGto {
+    parameters: SoapRadialIntegralGtoParameters,
+    /// Width of each primitive (un-normalized) GTO, indexed by `n`
+    primitive_widths: Vec<f64>,
+    /// Normalization of each primitive GTO, indexed by `n`
+    primitive_normalizations: Vec<f64>,
+    /// Lower-triangular transform from the primitive GTOs to an orthonormal
+    /// basis, obtained from the Cholesky factorization of the primitive
+    /// overlap matrix: `orthonormal_n = sum_m transform[n, m] * primitive_m`
+    transform: Array2<f64>,
+}
+
+impl SoapRadialIntegralGto {
+    /// Create a new `SoapRadialIntegralGto` for the given `parameters`
+    pub fn new(parameters: SoapRadialIntegralGtoParameters) -> Result<SoapRadialIntegralGto, Error> {
+        parameters.validate()?;
+
+        let max_radial = parameters.max_radial;
+        let mut primitive_widths = Vec::with_capacity(max_radial);
+        for n in 0..max_radial {
+            // primitive GTOs get wider (reach further) as `n` increases, so
+            // that the full basis spans the whole cutoff radius
+            let width = parameters.cutoff * f64::max((n as f64).sqrt(), 1.0) / max_radial as f64;
+            primitive_widths.push(width);
+        }
+
+        let mut primitive_normalizations = Vec::with_capacity(max_radial);
+        for (n, &sigma) in primitive_widths.iter().enumerate() {
+            // normalization such that `\int_0^\infty (N_n r^n e^{-r^2/(2 sigma^2)})^2 r^2 dr = 1`
+            let n = n as f64;
+            let power = 2.0 * n + 3.0;
+            let normalization = f64::sqrt(2.0 / (sigma.powf(power) * gamma(n + 1.5)));
+            primitive_normalizations.push(normalization);
+        }
+
+        let overlap = primitive_overlap_matrix(&primitive_widths, &primitive_normalizations);
+        let transform = orthonormalization_transform(&overlap)?;
+
+        return Ok(SoapRadialIntegralGto {
+            parameters,
+            primitive_widths,
+            primitive_normalizations,
+            transform,
+        });
+    }
+}
+
+/// Overlap matrix `S_nm = \int_0^\infty \phi_n(r) \phi_m(r) r^2 dr` between
+/// the (normalized) primitive GTOs.
+fn primitive_overlap_matrix(widths: &[f64], normalizations: &[f64]) -> Array2<f64> {
+    let max_radial = widths.len();
+    let mut overlap = Array2::from_elem((max_radial, max_radial), 0.0);
+    for n in 0..max_radial {
+        for m in 0..max_radial {
+            let a = 0.5 / (widths[n] * widths[n]) + 0.5 / (widths[m] * widths[m]);
+            let power = (n + m) as f64 + 3.0;
+            overlap[[n, m]] = normalizations[n] * normalizations[m]
+                * 0.5 * gamma(power / 2.0) / a.powf(power / 2.0);
+        }
+    }
+    return overlap;
+}
+
+/// Compute the lower-triangular transform `T` such that `T S T^T = I`, from
+/// the Cholesky factorization `S = L L^T` of the (symmetric, positive
+/// definite) overlap matrix `S`: `T` is simply `L^{-1}`.
+fn orthonormalization_transform(overlap: &Array2<f64>) -> Result<Array2<f64>, Error> {
+    let n = overlap.shape()[0];
+    let mut cholesky = Array2::from_elem((n, n), 0.0);
+
+    for i in 0..n {
+        for j in 0..=i {
+            let mut sum = overlap[[i, j]];
+            for k in 0..j {
+                sum -= cholesky[[i, k]] * cholesky[[j, k]];
+            }
+
+            if i == j {
+                if sum <= 0.0 {
+                    return Err(Error::InvalidParameter(
+                        "radial basis overlap matrix is not positive definite, \
+                         this should not happen with a valid set of parameters".into()
+                    ));
+                }
+                cholesky[[i, j]] = sum.sqrt();
+            } else {
+                cholesky[[i, j]] = sum / cholesky[[j, j]];
+            }
+        }
+    }
+
+    // invert the lower triangular Cholesky factor by forward substitution
+    let mut transform = Array2::from_elem((n, n), 0.0);
+    for i in 0..n {
+        transform[[i, i]] = 1.0 / cholesky[[i, i]];
+        for j in 0..i {
+            let mut sum = 0.0;
+            for k in j..i {
+                sum += cholesky[[i, k]] * transform[[k, j]];
+            }
+            transform[[i, j]] = -sum / cholesky[[i, i]];
+        }
+    }
+
+    return Ok(transform);
+}
+
+impl SoapRadialIntegral for SoapRadialIntegralGto {
+    #[allow(clippy::needless_range_loop)]
+    fn compute(
+        &self,
+        rij: f64,
+        species_neighbor: usize,
+        mut values: ArrayViewMut2<f64>,
+        mut gradients: Option<ArrayViewMut2<f64>>,
+    ) {
+        let max_radial = self.parameters.max_radial;
+        let max_angular = self.parameters.max_angular;
+
+        // the atomic density width (and therefore the Gaussian prefactor and
+        // the Bessel function argument below) can depend on the neighbor
+        // species, so it is recomputed here for every call instead of being
+        // cached at construction time
+        let sigma = self.parameters.atomic_gaussian_width.get(species_neighbor);
+        let sigma2 = sigma * sigma;
+
+        // values/gradients of each primitive GTO, for every (n, l)
+        let mut primitive_values = Array2::from_elem((max_angular + 1, max_radial), 0.0);
+        let mut primitive_gradients = Array2::from_elem((max_angular + 1, max_radial), 0.0);
+
+        for n in 0..max_radial {
+            let sigma_n2 = self.primitive_widths[n] * self.primitive_widths[n];
+            let a = 0.5 / sigma_n2 + 0.5 / sigma2;
+            let b = rij / sigma2;
+            let c = 1.0 / (4.0 * a * sigma2 * sigma2);
+
+            let prefactor = self.primitive_normalizations[n]
+                * 4.0 * std::f64::consts::PI / (std::f64::consts::PI * sigma2).powf(0.75);
+            let gaussian = f64::exp(-rij * rij / (2.0 * sigma2));
+
+            for l in 0..=max_angular {
+                let p = (n + l) as f64 / 2.0 + 1.5;
+                let q = l as f64 + 1.5;
+                let z = c * rij * rij;
+
+                let hyp1f1 = kummer_1f1(p, q, z);
+                let rij_pow_l = if l == 0 { 1.0 } else { rij.powi(l as i32) };
+
+                // `\int_0^\infty r^{n+2} e^{-a r^2} i_l(b r) dr = norm * r_ij^l * 1F1(p, q, z)`
+                let norm = std::f64::consts::PI.sqrt() * gamma(p)
+                    / (2.0_f64.powi(l as i32 + 2) * gamma(q) * a.powf(p) * sigma2.powi(l as i32));
+                primitive_values[[l, n]] = prefactor * gaussian * rij_pow_l * norm * hyp1f1;
+
+                if gradients.is_some() {
+                    let hyp1f1_grad = if rij == 0.0 {
+                        0.0
+                    } else {
+                        (p / q) * kummer_1f1(p + 1.0, q + 1.0, z) * 2.0 * c * rij
+                    };
+
+                    let mut derivative = -rij / sigma2 * rij_pow_l * hyp1f1;
+                    if l > 0 {
+                        derivative += l as f64 * rij.powi(l as i32 - 1) * hyp1f1;
+                    }
+                    derivative += rij_pow_l * hyp1f1_grad;
+
+                    primitive_gradients[[l, n]] = prefactor * gaussian * norm * derivative;
+                }
+            }
+        }
+
+        // combine the primitive GTOs into the orthonormal basis through the
+        // transform computed in `new`
+        for l in 0..=max_angular {
+            for n in 0..max_radial {
+                let mut value = 0.0;
+                let mut gradient = 0.0;
+                for m in 0..=n {
+                    let coefficient = self.transform[[n, m]];
+                    value += coefficient * primitive_values[[l, m]];
+                    if gradients.is_some() {
+                        gradient += coefficient * primitive_gradients[[l, m]];
+                    }
+                }
+
+                values[[l, n]] = value;
+                if let Some(ref mut gradients) = gradients {
+                    gradients[[l, n]] = gradient;
+                }
+            }
+        }
+    }
+}
+
+/// Evaluate the confluent hypergeometric (Kummer) function `1F1(a, b, z)`
+/// through direct summation of its defining series. This converges for every
+/// finite `z`, and the values of `z` reached by the radial integral above
+/// stay small enough (bounded by the cutoff radius and the atomic Gaussian
+/// width) for the series to converge in a reasonable number of terms.
+fn kummer_1f1(a: f64, b: f64, z: f64) -> f64 {
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    for k in 0..500 {
+        term *= (a + k as f64) / (b + k as f64) * z / (k as f64 + 1.0);
+        sum += term;
+        if term.abs() < 1e-15 * sum.abs() {
+            break;
+        }
+    }
+    return sum;
+}
+
+/// Evaluate the Gamma function for the positive arguments (integers and
+/// half-integers) that show up in the GTO radial integral, using the Lanczos
+/// approximation.
+fn gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_93,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_571_6e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // reflection formula, not needed for the positive arguments used
+        // here but kept for completeness
+        return std::f64::consts::PI / (f64::sin(std::f64::consts::PI * x) * gamma(1.0 - x));
+    }
+
+    let x = x - 1.0;
+    let mut a = COEFFICIENTS[0];
+    let t = x + G + 0.5;
+    for (i, &coefficient) in COEFFICIENTS.iter().enumerate().skip(1) {
+        a += coefficient / (x + i as f64);
+    }
+
+    return f64::sqrt(2.0 * std::f64::consts::PI) * t.powf(x + 0.5) * f64::exp(-t) * a;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use super::*;
+
+    #[test]
+    fn gamma_matches_known_values() {
+        assert!((gamma(1.0) - 1.0).abs() < 1e-10);
+        assert!((gamma(0.5) - std::f64::consts::PI.sqrt()).abs() < 1e-10);
+        assert!((gamma(5.0) - 24.0).abs() < 1e-8);
+        assert!((gamma(1.5) - 0.5 * std::f64::consts::PI.sqrt()).abs() < 1e-10);
+    }
+
+    #[test]
+    fn kummer_1f1_matches_known_values() {
+        // 1F1(a, b, 0) == 1 for any a, b
+        assert!((kummer_1f1(2.3, 4.1, 0.0) - 1.0).abs() < 1e-12);
+
+        // 1F1(1, 2, z) == (e^z - 1) / z, a standard closed form
+        for &z in &[0.1, 1.0, 3.0] {
+            let reference = (f64::exp(z) - 1.0) / z;
+            assert!((kummer_1f1(1.0, 2.0, z) - reference).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn orthonormalization_transform_is_correct() {
+        let widths = vec![1.0, 1.5, 2.2];
+        let mut normalizations = Vec::new();
+        for (n, &sigma) in widths.iter().enumerate() {
+            let n = n as f64;
+            let power = 2.0 * n + 3.0;
+            normalizations.push(f64::sqrt(2.0 / (sigma.powf(power) * gamma(n + 1.5))));
+        }
+
+        let overlap = primitive_overlap_matrix(&widths, &normalizations);
+        let transform = orthonormalization_transform(&overlap).unwrap();
+
+        // by construction, `transform` should turn `overlap` into the
+        // identity matrix: `T S T^T == I`
+        let product = transform.dot(&overlap).dot(&transform.t());
+        for i in 0..widths.len() {
+            for j in 0..widths.len() {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((product[[i, j]] - expected).abs() < 1e-9, "{:?}", product);
+            }
+        }
+    }
+
+    #[test]
+    fn per_species_width_gives_different_values() {
+        let mut widths = BTreeMap::new();
+        widths.insert(0, 0.3);
+        widths.insert(1, 0.6);
+
+        let parameters = SoapRadialIntegralGtoParameters {
+            max_radial: 3,
+            max_angular: 2,
+            atomic_gaussian_width: AtomicGaussianWidth::PerSpecies(widths),
+            cutoff: 4.0,
+        };
+        let gto = SoapRadialIntegralGto::new(parameters).unwrap();
+
+        let shape = (3, 3);
+        let mut values_species_0 = Array2::from_elem(shape, 0.0);
+        let mut values_species_1 = Array2::from_elem(shape, 0.0);
+        gto.compute(1.2, 0, values_species_0.view_mut(), None);
+        gto.compute(1.2, 1, values_species_1.view_mut(), None);
+
+        assert_ne!(values_species_0, values_species_1);
+    }
+
+    #[test]
+    fn validate_rejects_non_positive_width() {
+        let base = SoapRadialIntegralGtoParameters {
+            max_radial: 3,
+            max_angular: 2,
+            atomic_gaussian_width: AtomicGaussianWidth::Constant(0.0),
+            cutoff: 4.0,
+        };
+        assert!(SoapRadialIntegralGto::new(base).is_err());
+
+        let mut widths = BTreeMap::new();
+        widths.insert(0, -1.0);
+        let per_species = SoapRadialIntegralGtoParameters {
+            max_radial: 3,
+            max_angular: 2,
+            atomic_gaussian_width: AtomicGaussianWidth::PerSpecies(widths),
+            cutoff: 4.0,
+        };
+        assert!(SoapRadialIntegralGto::new(per_species).is_err());
+    }
+}
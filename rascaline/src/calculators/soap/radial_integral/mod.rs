@@ -1,8 +1,83 @@
+use std::collections::BTreeMap;
+
 use ndarray::{ArrayViewMut2, Array2};
 
 use crate::Error;
 use crate::calculators::radial_basis::RadialBasis;
 
+/// Width of the atomic Gaussian density used in the SOAP radial integral,
+/// either a single value shared by all neighbor species, or a width that
+/// depends on the neighbor species, for adaptive/per-element smearing.
+#[derive(Debug, Clone)]
+pub enum AtomicGaussianWidth {
+    /// The same Gaussian width is used for all neighbor species
+    Constant(f64),
+    /// Gaussian width depending on the neighbor species, for per-element
+    /// smearing
+    PerSpecies(BTreeMap<usize, f64>),
+}
+
+impl AtomicGaussianWidth {
+    /// Get the width of the atomic density for a neighbor of the given
+    /// `species`.
+    ///
+    /// For `PerSpecies`, `species` must be one of the keys inserted in the
+    /// map: this is a hard precondition, checked here with a panic rather
+    /// than an `Error`, since `compute` (the only caller) has no way to
+    /// propagate a `Result` either. Callers that only learn the full set of
+    /// neighbor species at runtime should check coverage upfront with
+    /// [`AtomicGaussianWidth::check_species_coverage`] instead of relying on
+    /// this panic.
+    pub fn get(&self, species: usize) -> f64 {
+        match self {
+            AtomicGaussianWidth::Constant(width) => *width,
+            AtomicGaussianWidth::PerSpecies(widths) => {
+                widths.get(&species).copied().unwrap_or_else(|| {
+                    panic!("missing atomic Gaussian width for species {}", species)
+                })
+            }
+        }
+    }
+
+    /// Check that every species in `species` has an associated width,
+    /// before any of them reaches [`AtomicGaussianWidth::get`].
+    ///
+    /// This is a no-op for `Constant`. For `PerSpecies`, it lets a caller
+    /// that knows the full set of neighbor species upfront (e.g. once a
+    /// system has been loaded) turn a missing width into an `Error` instead
+    /// of a panic deep inside the radial integral.
+    pub fn check_species_coverage(&self, species: &[usize]) -> Result<(), Error> {
+        if let AtomicGaussianWidth::PerSpecies(widths) = self {
+            let mut missing: Vec<usize> = species.iter()
+                .copied()
+                .filter(|species| !widths.contains_key(species))
+                .collect();
+            missing.sort_unstable();
+            missing.dedup();
+
+            if !missing.is_empty() {
+                return Err(Error::InvalidParameter(format!(
+                    "missing atomic Gaussian width for species {:?}", missing
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<f64> for AtomicGaussianWidth {
+    fn from(width: f64) -> AtomicGaussianWidth {
+        AtomicGaussianWidth::Constant(width)
+    }
+}
+
+impl From<BTreeMap<usize, f64>> for AtomicGaussianWidth {
+    fn from(widths: BTreeMap<usize, f64>) -> AtomicGaussianWidth {
+        AtomicGaussianWidth::PerSpecies(widths)
+    }
+}
+
 /// A `SoapRadialIntegral` computes the SOAP radial integral on a given radial
 /// basis.
 ///
@@ -15,9 +90,9 @@ use crate::calculators::radial_basis::RadialBasis;
 #[allow(clippy::doc_markdown)]
 pub trait SoapRadialIntegral: std::panic::RefUnwindSafe + Send {
     /// Compute the radial integral for a single `distance` between two atoms
-    /// and store the resulting data in the `(max_angular + 1) x max_radial`
-    /// array `values`. If `gradients` is `Some`, also compute and store
-    /// gradients there.
+    /// (the neighbor being of species `species_neighbor`), and store the
+    /// resulting data in the `(max_angular + 1) x max_radial` array `values`.
+    /// If `gradients` is `Some`, also compute and store gradients there.
     ///
     /// The radial integral $I_{nl}$ is defined as "the non-spherical harmonics
     /// part of the spherical expansion". Depending on the atomic density,
@@ -28,7 +103,8 @@ pub trait SoapRadialIntegral: std::panic::RefUnwindSafe + Send {
     ///
     /// $$ I_{nl}(r_{ij}) = R_{nl}(r_{ij}) $$
     ///
-    /// For a Gaussian atomic density with a width of $\sigma$, the radial
+    /// For a Gaussian atomic density with a width of $\sigma$ (which may
+    /// depend on `species_neighbor`, see [`AtomicGaussianWidth`]), the radial
     /// integral reduces to:
     ///
     /// $$
@@ -48,7 +124,7 @@ pub trait SoapRadialIntegral: std::panic::RefUnwindSafe + Send {
     /// $$
     ///
     /// where $P_l$ is the l-th Legendre polynomial.
-    fn compute(&self, rij: f64, values: ArrayViewMut2<f64>, gradients: Option<ArrayViewMut2<f64>>);
+    fn compute(&self, rij: f64, species_neighbor: usize, values: ArrayViewMut2<f64>, gradients: Option<ArrayViewMut2<f64>>);
 }
 
 mod gto;
@@ -58,11 +134,11 @@ mod spline;
 pub use self::spline::{SoapRadialIntegralSpline, SoapRadialIntegralSplineParameters};
 
 /// Parameters controlling the radial integral for SOAP
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct SoapRadialIntegralParameters {
     pub max_radial: usize,
     pub max_angular: usize,
-    pub atomic_gaussian_width: f64,
+    pub atomic_gaussian_width: AtomicGaussianWidth,
     pub cutoff: f64,
 }
 
@@ -82,21 +158,33 @@ impl SoapRadialIntegralCache {
     pub fn new(radial_basis: RadialBasis, parameters: SoapRadialIntegralParameters) -> Result<Self, Error> {
         let code = match radial_basis {
             RadialBasis::Gto {splined_radial_integral, spline_accuracy} => {
-                let parameters = SoapRadialIntegralGtoParameters {
-                    max_radial: parameters.max_radial,
-                    max_angular: parameters.max_angular,
+                // `atomic_gaussian_width` is not `Copy` (it may own a
+                // per-species `BTreeMap`), so grab the remaining, `Copy`
+                // fields before moving it into `SoapRadialIntegralGtoParameters`
+                let max_radial = parameters.max_radial;
+                let max_angular = parameters.max_angular;
+                let cutoff = parameters.cutoff;
+
+                let gto_parameters = SoapRadialIntegralGtoParameters {
+                    max_radial,
+                    max_angular,
                     atomic_gaussian_width: parameters.atomic_gaussian_width,
-                    cutoff: parameters.cutoff,
+                    cutoff,
                 };
-                let gto = SoapRadialIntegralGto::new(parameters)?;
+                let gto = SoapRadialIntegralGto::new(gto_parameters)?;
 
                 if splined_radial_integral {
                     let parameters = SoapRadialIntegralSplineParameters {
-                        max_radial: parameters.max_radial,
-                        max_angular: parameters.max_angular,
-                        cutoff: parameters.cutoff,
+                        max_radial,
+                        max_angular,
+                        cutoff,
                     };
 
+                    // `SoapRadialIntegralSpline` builds one spline per
+                    // neighbor species it is asked to evaluate, so a
+                    // `PerSpecies` width transparently gets one spline per
+                    // species here; species with the same width do not
+                    // share a spline
                     Box::new(SoapRadialIntegralSpline::with_accuracy(
                         parameters, spline_accuracy, gto
                     )?)
@@ -124,21 +212,89 @@ impl SoapRadialIntegralCache {
         return Ok(SoapRadialIntegralCache { code, values, gradients });
     }
 
-    /// Run the calculation, the results are stored inside `self.values` and
-    /// `self.gradients`
-    pub fn compute(&mut self, distance: f64, gradients: bool) {
+    /// Run the calculation for a neighbor of the given `species`, the
+    /// results are stored inside `self.values` and `self.gradients`
+    pub fn compute(&mut self, distance: f64, species_neighbor: usize, gradients: bool) {
         if gradients {
             self.code.compute(
                 distance,
+                species_neighbor,
                 self.values.view_mut(),
                 Some(self.gradients.view_mut()),
             );
         } else {
             self.code.compute(
                 distance,
+                species_neighbor,
                 self.values.view_mut(),
                 None,
             );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_species_coverage_catches_missing_species() {
+        let mut widths = BTreeMap::new();
+        widths.insert(0, 0.3);
+        widths.insert(1, 0.5);
+        let width = AtomicGaussianWidth::PerSpecies(widths);
+
+        assert!(width.check_species_coverage(&[0, 1, 0]).is_ok());
+        assert!(width.check_species_coverage(&[0, 1, 6]).is_err());
+
+        // `Constant` covers every species
+        assert!(AtomicGaussianWidth::Constant(0.3).check_species_coverage(&[0, 1, 6]).is_ok());
+    }
+
+    fn per_species_parameters() -> SoapRadialIntegralParameters {
+        let mut widths = BTreeMap::new();
+        widths.insert(0, 0.3);
+        widths.insert(1, 0.5);
+
+        SoapRadialIntegralParameters {
+            max_radial: 3,
+            max_angular: 2,
+            atomic_gaussian_width: AtomicGaussianWidth::PerSpecies(widths),
+            cutoff: 4.0,
+        }
+    }
+
+    #[test]
+    fn cache_new_builds_gto_from_per_species_width() {
+        let radial_basis = RadialBasis::Gto {
+            splined_radial_integral: false,
+            spline_accuracy: 1e-8,
+        };
+
+        let mut cache = SoapRadialIntegralCache::new(radial_basis, per_species_parameters()).unwrap();
+
+        cache.compute(1.0, 0, false);
+        let species_0 = cache.values.clone();
+        cache.compute(1.0, 1, false);
+        let species_1 = cache.values.clone();
+
+        assert_ne!(species_0, species_1);
+    }
+
+    #[test]
+    fn cache_new_builds_splined_gto_from_per_species_width() {
+        let radial_basis = RadialBasis::Gto {
+            splined_radial_integral: true,
+            spline_accuracy: 1e-8,
+        };
+
+        let mut cache = SoapRadialIntegralCache::new(radial_basis, per_species_parameters()).unwrap();
+
+        cache.compute(1.0, 0, false);
+        let species_0 = cache.values.clone();
+        cache.compute(1.0, 1, false);
+        let species_1 = cache.values.clone();
+
+        assert_ne!(species_0, species_1);
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use ndarray::{Array2, ArrayViewMut2};
+
+use crate::Error;
+use super::SoapRadialIntegral;
+
+/// Parameters controlling a [`SoapRadialIntegralSpline`]
+#[derive(Debug, Clone)]
+pub struct SoapRadialIntegralSplineParameters {
+    /// Number of radial basis function to use
+    pub max_radial: usize,
+    /// Number of spherical harmonics to compute
+    pub max_angular: usize,
+    /// Cutoff radius, after which the radial integral is zero
+    pub cutoff: f64,
+}
+
+/// A single node of a cubic Hermite spline: the value and the gradient of
+/// every `(l, n)` component of the radial integral at a given distance.
+#[derive(Debug, Clone)]
+struct SplineNode {
+    position: f64,
+    values: Array2<f64>,
+    gradients: Array2<f64>,
+}
+
+/// A cubic Hermite spline, with one `(l, n)`-shaped value/gradient pair per
+/// node, used to replace the evaluation of a (possibly expensive)
+/// [`SoapRadialIntegral`] with a cheap interpolation over its whole range.
+#[derive(Debug, Clone)]
+struct HermitSpline {
+    nodes: Vec<SplineNode>,
+}
+
+impl HermitSpline {
+    /// Build a new spline for `function`, adding nodes (starting from a
+    /// coarse, regularly spaced grid) until the cubic Hermite interpolation
+    /// between two consecutive nodes is accurate to `accuracy` everywhere in
+    /// `[0, cutoff]`, or until `MAX_NODES` is reached.
+    fn with_accuracy(
+        cutoff: f64,
+        accuracy: f64,
+        mut function: impl FnMut(f64) -> (Array2<f64>, Array2<f64>),
+    ) -> HermitSpline {
+        const MAX_NODES: usize = 4096;
+        const INITIAL_NODES: usize = 8;
+
+        let mut positions: Vec<f64> = (0..=INITIAL_NODES)
+            .map(|i| cutoff * i as f64 / INITIAL_NODES as f64)
+            .collect();
+
+        loop {
+            let nodes: Vec<SplineNode> = positions.iter().map(|&position| {
+                let (values, gradients) = function(position);
+                SplineNode { position, values, gradients }
+            }).collect();
+
+            if nodes.len() >= MAX_NODES {
+                return HermitSpline { nodes };
+            }
+
+            let mut new_positions = Vec::new();
+            for window in nodes.windows(2) {
+                let (left, right) = (&window[0], &window[1]);
+                let middle = 0.5 * (left.position + right.position);
+                let (reference, _) = function(middle);
+                let interpolated = hermite_interpolate(left, right, middle).0;
+
+                let mut max_error: f64 = 0.0;
+                for (r, i) in reference.iter().zip(interpolated.iter()) {
+                    max_error = max_error.max((r - i).abs());
+                }
+
+                if max_error > accuracy {
+                    new_positions.push(middle);
+                }
+            }
+
+            if new_positions.is_empty() {
+                return HermitSpline { nodes };
+            }
+
+            positions = nodes.iter().map(|node| node.position)
+                .chain(new_positions)
+                .collect();
+            positions.sort_by(|a, b| a.partial_cmp(b).expect("NaN in spline node positions"));
+        }
+    }
+
+    fn evaluate(&self, x: f64, mut values: ArrayViewMut2<f64>, gradients: Option<ArrayViewMut2<f64>>) {
+        let x = x.clamp(self.nodes[0].position, self.nodes[self.nodes.len() - 1].position);
+
+        // binary search for the interval containing `x`
+        let index = match self.nodes.binary_search_by(|node| {
+            node.position.partial_cmp(&x).expect("NaN in spline position")
+        }) {
+            Ok(index) => index.min(self.nodes.len() - 2),
+            Err(index) => (index - 1).min(self.nodes.len() - 2),
+        };
+
+        let (interpolated_values, interpolated_gradients) = hermite_interpolate(
+            &self.nodes[index], &self.nodes[index + 1], x
+        );
+
+        values.assign(&interpolated_values);
+        if let Some(mut gradients) = gradients {
+            gradients.assign(&interpolated_gradients);
+        }
+    }
+}
+
+/// Cubic Hermite interpolation of the value and gradient stored at `left`
+/// and `right` (with `left.position <= x <= right.position`), returning the
+/// interpolated values and their derivative with respect to `x`.
+fn hermite_interpolate(left: &SplineNode, right: &SplineNode, x: f64) -> (Array2<f64>, Array2<f64>) {
+    let dx = right.position - left.position;
+    let t = (x - left.position) / dx;
+
+    let h00 = 2.0 * t.powi(3) - 3.0 * t.powi(2) + 1.0;
+    let h10 = t.powi(3) - 2.0 * t.powi(2) + t;
+    let h01 = -2.0 * t.powi(3) + 3.0 * t.powi(2);
+    let h11 = t.powi(3) - t.powi(2);
+
+    let d00 = 6.0 * t.powi(2) - 6.0 * t;
+    let d10 = 3.0 * t.powi(2) - 4.0 * t + 1.0;
+    let d01 = -6.0 * t.powi(2) + 6.0 * t;
+    let d11 = 3.0 * t.powi(2) - 2.0 * t;
+
+    let values = h00 * &left.values + h10 * dx * &left.gradients
+        + h01 * &right.values + h11 * dx * &right.gradients;
+
+    let gradients = (d00 * &left.values + d10 * dx * &left.gradients
+        + d01 * &right.values + d11 * dx * &right.gradients) / dx;
+
+    return (values, gradients);
+}
+
+/// Where a [`SoapRadialIntegralSpline`] gets its data from.
+enum SplineSource {
+    /// A single spline, shared for every neighbor species: used for
+    /// [`SoapRadialIntegralSpline::from_tabulated`], where the data does not
+    /// depend on the neighbor species.
+    Fixed(HermitSpline),
+    /// One spline per distinct neighbor species, built lazily the first time
+    /// [`SoapRadialIntegralSpline::compute`] is called for that species: this
+    /// transparently supports a [`super::AtomicGaussianWidth::PerSpecies`]
+    /// width on the wrapped `function` without having to enumerate the
+    /// neighbor species up front.
+    PerSpecies {
+        function: Box<dyn SoapRadialIntegral>,
+        accuracy: f64,
+        splines: Mutex<BTreeMap<usize, HermitSpline>>,
+    },
+}
+
+/// `SoapRadialIntegralSpline` replaces a [`SoapRadialIntegral`] with a cubic
+/// Hermite spline interpolation of its values, which is usually much cheaper
+/// to evaluate.
+///
+/// When splining a radial integral whose atomic Gaussian width depends on
+/// the neighbor species, a single spline can not be shared between species:
+/// instead, `SoapRadialIntegralSpline` builds one spline per distinct
+/// neighbor species it is asked to evaluate, the first time `compute` is
+/// called with that species.
+pub struct SoapRadialIntegralSpline {
+    parameters: SoapRadialIntegralSplineParameters,
+    source: SplineSource,
+}
+
+impl SoapRadialIntegralSpline {
+    /// Create a new `SoapRadialIntegralSpline` taking values from `function`,
+    /// building new splines (one per neighbor species) on demand, accurate to
+    /// `accuracy`.
+    pub fn with_accuracy(
+        parameters: SoapRadialIntegralSplineParameters,
+        accuracy: f64,
+        function: impl SoapRadialIntegral + 'static,
+    ) -> Result<SoapRadialIntegralSpline, Error> {
+        if accuracy <= 0.0 || !accuracy.is_finite() {
+            return Err(Error::InvalidParameter(
+                "spline accuracy must be a positive, finite number".into()
+            ));
+        }
+
+        return Ok(SoapRadialIntegralSpline {
+            parameters,
+            source: SplineSource::PerSpecies {
+                function: Box::new(function),
+                accuracy,
+                splines: Mutex::new(BTreeMap::new()),
+            },
+        });
+    }
+
+    /// Create a new `SoapRadialIntegralSpline` from explicit, user-provided
+    /// `points`, shared by all neighbor species.
+    pub fn from_tabulated(
+        parameters: SoapRadialIntegralSplineParameters,
+        points: Vec<(f64, Array2<f64>, Array2<f64>)>,
+    ) -> Result<SoapRadialIntegralSpline, Error> {
+        if points.len() < 2 {
+            return Err(Error::InvalidParameter(
+                "need at least two points to build a tabulated radial integral".into()
+            ));
+        }
+
+        let mut nodes: Vec<SplineNode> = points.into_iter()
+            .map(|(position, values, gradients)| SplineNode { position, values, gradients })
+            .collect();
+        nodes.sort_by(|a, b| a.position.partial_cmp(&b.position).expect("NaN in tabulated positions"));
+
+        return Ok(SoapRadialIntegralSpline {
+            parameters,
+            source: SplineSource::Fixed(HermitSpline { nodes }),
+        });
+    }
+
+    fn new_spline_for(&self, function: &dyn SoapRadialIntegral, accuracy: f64) -> impl Fn(usize) -> HermitSpline + '_ {
+        let shape = (self.parameters.max_angular + 1, self.parameters.max_radial);
+        let cutoff = self.parameters.cutoff;
+
+        move |species_neighbor: usize| {
+            HermitSpline::with_accuracy(cutoff, accuracy, |distance| {
+                let mut values = Array2::from_elem(shape, 0.0);
+                let mut gradients = Array2::from_elem(shape, 0.0);
+                function.compute(distance, species_neighbor, values.view_mut(), Some(gradients.view_mut()));
+                (values, gradients)
+            })
+        }
+    }
+}
+
+impl SoapRadialIntegral for SoapRadialIntegralSpline {
+    fn compute(
+        &self,
+        rij: f64,
+        species_neighbor: usize,
+        values: ArrayViewMut2<f64>,
+        gradients: Option<ArrayViewMut2<f64>>,
+    ) {
+        match &self.source {
+            SplineSource::Fixed(spline) => spline.evaluate(rij, values, gradients),
+            SplineSource::PerSpecies { function, accuracy, splines } => {
+                let mut splines = splines.lock().expect("mutex was poisoned");
+                if !splines.contains_key(&species_neighbor) {
+                    let build = self.new_spline_for(&**function, *accuracy);
+                    splines.insert(species_neighbor, build(species_neighbor));
+                }
+
+                splines.get(&species_neighbor)
+                    .expect("just inserted")
+                    .evaluate(rij, values, gradients);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use super::*;
+    use super::super::{AtomicGaussianWidth, SoapRadialIntegralGto, SoapRadialIntegralGtoParameters};
+
+    fn sample_gto() -> SoapRadialIntegralGto {
+        let mut widths = BTreeMap::new();
+        widths.insert(0, 0.3);
+        widths.insert(1, 0.5);
+
+        SoapRadialIntegralGto::new(SoapRadialIntegralGtoParameters {
+            max_radial: 4,
+            max_angular: 3,
+            atomic_gaussian_width: AtomicGaussianWidth::PerSpecies(widths),
+            cutoff: 5.0,
+        }).unwrap()
+    }
+
+    #[test]
+    fn spline_stays_within_accuracy_of_the_underlying_function() {
+        let accuracy = 1e-7;
+        let gto = sample_gto();
+        let cutoff = 5.0;
+
+        let parameters = SoapRadialIntegralSplineParameters {
+            max_radial: 4,
+            max_angular: 3,
+            cutoff,
+        };
+        let splined = SoapRadialIntegralSpline::with_accuracy(parameters, accuracy, gto).unwrap();
+
+        let reference = sample_gto();
+        let shape = (4, 4);
+        for &species in &[0, 1] {
+            for i in 0..=50 {
+                let distance = cutoff * i as f64 / 50.0;
+
+                let mut reference_values = Array2::from_elem(shape, 0.0);
+                reference.compute(distance, species, reference_values.view_mut(), None);
+
+                let mut splined_values = Array2::from_elem(shape, 0.0);
+                splined.compute(distance, species, splined_values.view_mut(), None);
+
+                for (r, s) in reference_values.iter().zip(splined_values.iter()) {
+                    // allow some slack over the node-to-node `accuracy` bound,
+                    // since we are not only sampling at the nodes themselves
+                    assert!((r - s).abs() < 100.0 * accuracy, "{} vs {}", r, s);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tabulated_spline_reproduces_its_input_points() {
+        let shape = (2, 2);
+        let points = vec![
+            (0.0, Array2::from_elem(shape, 0.0), Array2::from_elem(shape, 1.0)),
+            (1.0, Array2::from_elem(shape, 1.0), Array2::from_elem(shape, 1.0)),
+            (2.0, Array2::from_elem(shape, 2.0), Array2::from_elem(shape, 1.0)),
+        ];
+
+        let parameters = SoapRadialIntegralSplineParameters {
+            max_radial: 2,
+            max_angular: 1,
+            cutoff: 2.0,
+        };
+        let splined = SoapRadialIntegralSpline::from_tabulated(parameters, points).unwrap();
+
+        let mut values = Array2::from_elem(shape, 0.0);
+        splined.compute(1.0, 0, values.view_mut(), None);
+        for value in values.iter() {
+            assert!((value - 1.0).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::ops::{Index, Neg};
+
+mod simple_system;
+pub use self::simple_system::SimpleSystem;
+
+/// A single neighbor pair within a cutoff distance, as found while building a
+/// neighbor list.
+///
+/// Since a pair of atoms can be within cutoff of each other through more than
+/// one periodic image (e.g. when the cutoff is larger than half the cell
+/// length, or for an atom's own periodic images), `cell_shift` disambiguates
+/// which image of `second` this particular pair refers to.
+#[derive(Clone, Copy, Debug)]
+pub struct Pair {
+    /// Index of the first atom in the pair
+    pub first: usize,
+    /// Index of the second atom in the pair
+    pub second: usize,
+    /// Distance between the two atoms, for this particular periodic image
+    pub distance: f64,
+    /// Vector from the first to the second atom, for this particular
+    /// periodic image
+    pub vector: Vector3D,
+    /// Periodic lattice shift `[na, nb, nc]` applied to `second` to reach
+    /// this particular image of the pair
+    pub cell_shift: [i32; 3],
+}
+
+/// Trait implemented by types that can provide atomic positions, species and
+/// unit cell information to the calculators, together with the corresponding
+/// neighbor list.
+pub trait System {
+    /// Number of atoms in this system
+    fn size(&self) -> usize;
+    /// Cartesian positions of all the atoms in this system
+    fn positions(&self) -> &[Vector3D];
+    /// Species of all the atoms in this system
+    fn species(&self) -> &[usize];
+    /// Unit cell of this system
+    fn cell(&self) -> UnitCell;
+
+    /// Compute the neighbor list for the given `cutoff`, to be later
+    /// accessed through `pairs`/`pairs_containing`
+    fn compute_neighbors(&mut self, cutoff: f64);
+    /// All the pairs in the neighbor list computed by the last call to
+    /// `compute_neighbors`
+    fn pairs(&self) -> &[Pair];
+    /// Pairs from the neighbor list that contain the atom at index `center`
+    fn pairs_containing(&self, center: usize) -> &[Pair];
+}
+
+/// Unit cell of a system, stored as the matrix of its three lattice vectors
+#[derive(Clone, Copy, Debug)]
+pub struct UnitCell {
+    matrix: [[f64; 3]; 3],
+}
+
+impl UnitCell {
+    /// Cubic unit cell with the given side `length`
+    pub fn cubic(length: f64) -> UnitCell {
+        UnitCell {
+            matrix: [
+                [length, 0.0, 0.0],
+                [0.0, length, 0.0],
+                [0.0, 0.0, length],
+            ],
+        }
+    }
+
+    /// Triclinic unit cell with the given lattice vectors
+    pub fn triclinic(a: Vector3D, b: Vector3D, c: Vector3D) -> UnitCell {
+        UnitCell {
+            matrix: [
+                [a[0], a[1], a[2]],
+                [b[0], b[1], b[2]],
+                [c[0], c[1], c[2]],
+            ],
+        }
+    }
+
+    /// Matrix containing the three lattice vectors of this cell as rows
+    pub fn matrix(&self) -> [[f64; 3]; 3] {
+        self.matrix
+    }
+}
+
+/// A 3D vector, used for atomic positions and displacements between atoms
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vector3D([f64; 3]);
+
+impl Vector3D {
+    /// Create a new vector with the given components
+    pub fn new(x: f64, y: f64, z: f64) -> Vector3D {
+        Vector3D([x, y, z])
+    }
+
+    /// Squared euclidean norm of this vector
+    pub fn norm2(&self) -> f64 {
+        self.0[0] * self.0[0] + self.0[1] * self.0[1] + self.0[2] * self.0[2]
+    }
+}
+
+impl Index<usize> for Vector3D {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &f64 {
+        &self.0[index]
+    }
+}
+
+impl Neg for Vector3D {
+    type Output = Vector3D;
+
+    fn neg(self) -> Vector3D {
+        Vector3D([-self.0[0], -self.0[1], -self.0[2]])
+    }
+}
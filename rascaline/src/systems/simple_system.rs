@@ -1,5 +1,26 @@
 use super::{UnitCell, System, Vector3D, Pair};
 
+/// Below this many atoms, the linked cell list does not pay for its own
+/// bookkeeping compared to the brute force double loop.
+const MIN_ATOMS_FOR_CELL_LIST: usize = 100;
+
+/// Minimum number of cells required along each lattice direction for the
+/// grid to be worth building; very small or very skewed cells fall back to
+/// the brute force path instead.
+const MIN_CELLS_PER_DIRECTION: usize = 3;
+
+/// The `[0, 0, 0]` cell together with the 13 half-space neighbor offsets
+/// used to scan the 26 neighbors of a cell in a linked cell list without
+/// visiting the same pair of cells twice.
+const HALF_CELL_NEIGHBORS: [[i64; 3]; 14] = [
+    [0, 0, 0],
+    [1, 0, 0], [0, 1, 0], [0, 0, 1],
+    [1, 1, 0], [1, -1, 0],
+    [1, 0, 1], [1, 0, -1],
+    [0, 1, 1], [0, 1, -1],
+    [1, 1, 1], [1, 1, -1], [1, -1, 1], [1, -1, -1],
+];
+
 #[derive(Clone, Debug)]
 struct CrappyNeighborsList {
     cutoff: f64,
@@ -10,42 +31,34 @@ struct CrappyNeighborsList {
 impl CrappyNeighborsList {
     #[time_graph::instrument(name = "neighbor list")]
     pub fn new<S: System + ?Sized>(system: &S, cutoff: f64) -> CrappyNeighborsList {
-        let cutoff2 = cutoff * cutoff;
-        let cell = system.cell();
         let natoms = system.size();
-        let positions = system.positions();
-
-        let mut pairs = Vec::new();
-        // crappy O(n^2) implementation, looping over all atoms in the system
-        for i in 0..natoms {
-            for j in (i + 1)..natoms {
-                let mut vector = positions[j] - positions[i];
-                cell.vector_image(&mut vector);
-                let distance2 = vector.norm2();
-                if distance2 < cutoff2 {
-                    if i < j {
-                        pairs.push(Pair {
-                            first: i,
-                            second: j,
-                            distance: distance2.sqrt(),
-                            vector: vector
-                        });
-                    } else {
-                        pairs.push(Pair {
-                            first: j,
-                            second: i,
-                            distance: distance2.sqrt(),
-                            vector: -vector
-                        });
-                    }
-                }
-            }
-        }
+
+        // the cell list is only worth building for large enough, reasonably
+        // shaped systems; fall back to the brute force loop otherwise
+        let pairs = linked_cell_list(system, cutoff)
+            .unwrap_or_else(|| brute_force_pairs(system, cutoff));
 
         let mut pairs_by_center = vec![Vec::new(); natoms];
         for pair in &pairs {
-            pairs_by_center[pair.first].push(*pair);
-            pairs_by_center[pair.second].push(*pair);
+            if pair.first == pair.second {
+                // self pairs come from an atom's own periodic images; the
+                // `n`/`-n` half-space de-duplication in `shifts_in_range`
+                // only keeps one of the two directions, but both directions
+                // are physically distinct neighbors of this atom, so the
+                // mirror image needs to be registered explicitly instead of
+                // pushing the same direction twice
+                pairs_by_center[pair.first].push(*pair);
+                pairs_by_center[pair.first].push(Pair {
+                    first: pair.first,
+                    second: pair.second,
+                    distance: pair.distance,
+                    vector: -pair.vector,
+                    cell_shift: [-pair.cell_shift[0], -pair.cell_shift[1], -pair.cell_shift[2]],
+                });
+            } else {
+                pairs_by_center[pair.first].push(*pair);
+                pairs_by_center[pair.second].push(*pair);
+            }
         }
 
         return CrappyNeighborsList {
@@ -56,6 +69,312 @@ impl CrappyNeighborsList {
     }
 }
 
+/// Brute force O(n²) neighbor search, used for small systems and as a
+/// fallback when the unit cell is too small or too skewed to build a
+/// useful cell grid.
+///
+/// This enumerates every periodic translation that could bring a pair of
+/// atoms within `cutoff` of one another, instead of relying on a single
+/// minimum-image vector. This correctly handles cutoffs larger than half the
+/// cell length, including an atom's own periodic images.
+fn brute_force_pairs<S: System + ?Sized>(system: &S, cutoff: f64) -> Vec<Pair> {
+    let cutoff2 = cutoff * cutoff;
+    let cell = system.cell();
+    let natoms = system.size();
+    let positions = system.positions();
+    let matrix = cell.matrix();
+    let shift_range = periodic_shift_range(matrix, cutoff);
+
+    let mut pairs = Vec::new();
+    for i in 0..natoms {
+        for j in i..natoms {
+            let is_self_pair = i == j;
+            for shift in shifts_in_range(shift_range, is_self_pair) {
+                // an atom does not pair with its own, non-shifted self; this
+                // is already guaranteed here since `shifts_in_range` drops
+                // `[0, 0, 0]` from the half-space it returns for self pairs
+                let shift_cart = shift_to_cartesian(shift, matrix);
+                let vector = Vector3D::new(
+                    positions[j][0] - positions[i][0] + shift_cart[0],
+                    positions[j][1] - positions[i][1] + shift_cart[1],
+                    positions[j][2] - positions[i][2] + shift_cart[2],
+                );
+                let distance2 = vector.norm2();
+                if distance2 < cutoff2 {
+                    pairs.push(Pair {
+                        first: i,
+                        second: j,
+                        distance: distance2.sqrt(),
+                        vector: vector,
+                        cell_shift: shift,
+                    });
+                }
+            }
+        }
+    }
+
+    return pairs;
+}
+
+/// Try to build the neighbor list with a linked cell list, returning `None`
+/// when the system is too small or the cell is too skewed for the grid to
+/// have at least `MIN_CELLS_PER_DIRECTION` cells along every direction. The
+/// caller should fall back to [`brute_force_pairs`] in that case.
+fn linked_cell_list<S: System + ?Sized>(system: &S, cutoff: f64) -> Option<Vec<Pair>> {
+    let natoms = system.size();
+    if natoms < MIN_ATOMS_FOR_CELL_LIST {
+        return None;
+    }
+
+    let cell = system.cell();
+    let matrix = cell.matrix();
+    let n_cells = cell_grid_shape(matrix, cutoff)?;
+    let reciprocal = reciprocal_vectors(matrix)?;
+
+    let positions = system.positions();
+    let n_total_cells = n_cells[0] * n_cells[1] * n_cells[2];
+
+    // intrusive linked list: `head[cell]` is the last atom binned into
+    // `cell`, and `next[atom]` points to the next atom sharing the same
+    // cell (or `-1` at the end of the list)
+    let mut head = vec![-1_i64; n_total_cells];
+    let mut next = vec![-1_i64; natoms];
+    let mut cell_of = vec![[0usize; 3]; natoms];
+
+    for i in 0..natoms {
+        let fractional = fractional_coordinates(reciprocal, positions[i]);
+        let index = [
+            cell_bin(fractional[0], n_cells[0]),
+            cell_bin(fractional[1], n_cells[1]),
+            cell_bin(fractional[2], n_cells[2]),
+        ];
+        cell_of[i] = index;
+
+        let linear = linear_cell_index(index, n_cells);
+        next[i] = head[linear];
+        head[linear] = i as i64;
+    }
+
+    let cutoff2 = cutoff * cutoff;
+    let mut pairs = Vec::new();
+    for i in 0..natoms {
+        let [ix, iy, iz] = cell_of[i];
+        for offset in &HALF_CELL_NEIGHBORS {
+            // crossing the grid boundary along a direction is the same as
+            // crossing the unit cell boundary, since the grid exactly tiles
+            // one cell: the wrap direction directly gives the lattice shift
+            let (nx, sx) = wrapped_cell_index(ix as i64 + offset[0], n_cells[0]);
+            let (ny, sy) = wrapped_cell_index(iy as i64 + offset[1], n_cells[1]);
+            let (nz, sz) = wrapped_cell_index(iz as i64 + offset[2], n_cells[2]);
+            let neighbor = [nx, ny, nz];
+            let shift = [sx, sy, sz];
+
+            let mut current = head[linear_cell_index(neighbor, n_cells)];
+            while current >= 0 {
+                let j = current as usize;
+                // inside its own cell (`offset == [0, 0, 0]`), only look at
+                // `j > i` to match the `i < j` de-duplication of the brute
+                // force path; every other neighboring cell is only visited
+                // from one side thanks to the half-space offsets above
+                if *offset != [0, 0, 0] || j > i {
+                    let shift_cart = shift_to_cartesian(shift, matrix);
+                    let vector = Vector3D::new(
+                        positions[j][0] - positions[i][0] + shift_cart[0],
+                        positions[j][1] - positions[i][1] + shift_cart[1],
+                        positions[j][2] - positions[i][2] + shift_cart[2],
+                    );
+                    let distance2 = vector.norm2();
+                    if distance2 < cutoff2 {
+                        if i < j {
+                            pairs.push(Pair {
+                                first: i,
+                                second: j,
+                                distance: distance2.sqrt(),
+                                vector: vector,
+                                cell_shift: shift,
+                            });
+                        } else {
+                            pairs.push(Pair {
+                                first: j,
+                                second: i,
+                                distance: distance2.sqrt(),
+                                vector: -vector,
+                                cell_shift: [-shift[0], -shift[1], -shift[2]],
+                            });
+                        }
+                    }
+                }
+                current = next[j];
+            }
+        }
+    }
+
+    return Some(pairs);
+}
+
+/// Compute the number of cells along each lattice direction such that every
+/// cell edge is at least `cutoff` long, returning `None` if the cell is too
+/// small or too skewed to have at least `MIN_CELLS_PER_DIRECTION` cells in
+/// every direction.
+fn cell_grid_shape(matrix: [[f64; 3]; 3], cutoff: f64) -> Option<[usize; 3]> {
+    let distances = distances_between_faces(matrix)?;
+
+    let mut n_cells = [0usize; 3];
+    for k in 0..3 {
+        let n = f64::floor(distances[k] / cutoff) as usize;
+        if n < MIN_CELLS_PER_DIRECTION {
+            return None;
+        }
+        n_cells[k] = n;
+    }
+
+    return Some(n_cells);
+}
+
+/// Perpendicular distance between opposite faces of the cell along each
+/// lattice direction, i.e. how far apart two periodic images of the cell
+/// are along the direction perpendicular to the other two lattice vectors.
+/// Returns `None` for a degenerate or infinite cell.
+fn distances_between_faces(matrix: [[f64; 3]; 3]) -> Option<[f64; 3]> {
+    let [a, b, c] = matrix;
+    let volume = dot(a, cross(b, c)).abs();
+    if volume < 1e-9 {
+        return None;
+    }
+
+    return Some([
+        volume / norm(cross(b, c)),
+        volume / norm(cross(c, a)),
+        volume / norm(cross(a, b)),
+    ]);
+}
+
+/// Number of periodic images to try along each lattice direction for a pair
+/// search with the given `cutoff`, derived from `ceil(cutoff / d_perp)`
+/// where `d_perp` is the perpendicular distance between opposite cell faces.
+/// Returns `[0, 0, 0]` for a degenerate or infinite cell.
+fn periodic_shift_range(matrix: [[f64; 3]; 3], cutoff: f64) -> [i32; 3] {
+    match distances_between_faces(matrix) {
+        Some(distances) => [
+            f64::ceil(cutoff / distances[0]) as i32,
+            f64::ceil(cutoff / distances[1]) as i32,
+            f64::ceil(cutoff / distances[2]) as i32,
+        ],
+        None => [0, 0, 0],
+    }
+}
+
+/// Enumerate all lattice translations `[na, nb, nc]` with `|na| <= range[0]`
+/// (and similarly for `nb`/`nc`). When `half_space_only` is true, only one
+/// of each pair of opposite translations `n`/`-n` is kept (using
+/// lexicographic order), which is how self pairs (`i == j`) avoid being
+/// counted twice since `n` and `-n` describe the same bond.
+fn shifts_in_range(range: [i32; 3], half_space_only: bool) -> Vec<[i32; 3]> {
+    let mut shifts = Vec::new();
+    for na in -range[0]..=range[0] {
+        for nb in -range[1]..=range[1] {
+            for nc in -range[2]..=range[2] {
+                let shift = [na, nb, nc];
+                if half_space_only && !is_positive_half_space(shift) {
+                    continue;
+                }
+                shifts.push(shift);
+            }
+        }
+    }
+    return shifts;
+}
+
+fn is_positive_half_space(shift: [i32; 3]) -> bool {
+    if shift[0] != 0 {
+        return shift[0] > 0;
+    }
+    if shift[1] != 0 {
+        return shift[1] > 0;
+    }
+    return shift[2] > 0;
+}
+
+/// Cartesian displacement corresponding to the lattice translation `shift`
+fn shift_to_cartesian(shift: [i32; 3], matrix: [[f64; 3]; 3]) -> [f64; 3] {
+    let [a, b, c] = matrix;
+    let [na, nb, nc] = [shift[0] as f64, shift[1] as f64, shift[2] as f64];
+    [
+        na * a[0] + nb * b[0] + nc * c[0],
+        na * a[1] + nb * b[1] + nc * c[1],
+        na * a[2] + nb * b[2] + nc * c[2],
+    ]
+}
+
+/// Reciprocal lattice vectors (without the usual `2π` factor) used to
+/// convert a cartesian position to fractional coordinates: `b×c/V`, `c×a/V`
+/// and `a×b/V`, where `V` is the cell volume.
+fn reciprocal_vectors(matrix: [[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+    let [a, b, c] = matrix;
+    let volume = dot(a, cross(b, c));
+    if volume.abs() < 1e-9 {
+        return None;
+    }
+
+    return Some([
+        scale(cross(b, c), 1.0 / volume),
+        scale(cross(c, a), 1.0 / volume),
+        scale(cross(a, b), 1.0 / volume),
+    ]);
+}
+
+fn fractional_coordinates(reciprocal: [[f64; 3]; 3], position: Vector3D) -> [f64; 3] {
+    let position = [position[0], position[1], position[2]];
+    return [
+        dot(position, reciprocal[0]),
+        dot(position, reciprocal[1]),
+        dot(position, reciprocal[2]),
+    ];
+}
+
+fn cell_bin(fractional: f64, n_cells: usize) -> usize {
+    let wrapped = fractional.rem_euclid(1.0);
+    let bin = (wrapped * n_cells as f64) as usize;
+    return bin.min(n_cells - 1);
+}
+
+/// Wrap a (possibly out of bounds, by at most one cell) grid index back into
+/// `0..n_cells`, also returning the lattice shift (`-1`, `0` or `1`) crossing
+/// the grid boundary corresponds to, since the grid exactly tiles one cell.
+fn wrapped_cell_index(index: i64, n_cells: usize) -> (usize, i32) {
+    if index < 0 {
+        (index.rem_euclid(n_cells as i64) as usize, -1)
+    } else if index >= n_cells as i64 {
+        (index.rem_euclid(n_cells as i64) as usize, 1)
+    } else {
+        (index as usize, 0)
+    }
+}
+
+fn linear_cell_index(index: [usize; 3], n_cells: [usize; 3]) -> usize {
+    return index[0] + n_cells[0] * (index[1] + n_cells[1] * index[2]);
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn norm(a: [f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn scale(a: [f64; 3], factor: f64) -> [f64; 3] {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
 /// A simple implementation of `System` to use when no other is available
 #[derive(Clone, Debug)]
 pub struct SimpleSystem {
@@ -160,4 +479,135 @@ mod tests {
             Vector3D::new(5.0, 3.0, 4.0),
         ]);
     }
+
+    /// Minimal splitmix64-style generator, used only to get deterministic,
+    /// reproducible "random" positions in the tests below without pulling in
+    /// an external RNG dependency.
+    struct DeterministicRng(u64);
+
+    impl DeterministicRng {
+        fn next_u64(&mut self) -> u64 {
+            self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = self.0;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            return z ^ (z >> 31);
+        }
+
+        /// Uniform random number in `[0, 1)`
+        fn next_f64(&mut self) -> f64 {
+            return (self.next_u64() >> 11) as f64 * (1.0 / (1_u64 << 53) as f64);
+        }
+    }
+
+    fn fractional_to_cartesian(fractional: [f64; 3], matrix: [[f64; 3]; 3]) -> Vector3D {
+        Vector3D::new(
+            fractional[0] * matrix[0][0] + fractional[1] * matrix[1][0] + fractional[2] * matrix[2][0],
+            fractional[0] * matrix[0][1] + fractional[1] * matrix[1][1] + fractional[2] * matrix[2][1],
+            fractional[0] * matrix[0][2] + fractional[1] * matrix[1][2] + fractional[2] * matrix[2][2],
+        )
+    }
+
+    /// Build a system with `natoms` atoms at random fractional coordinates
+    /// inside `cell`, using `seed` to get a reproducible set of positions.
+    fn random_system(cell: UnitCell, natoms: usize, seed: u64) -> SimpleSystem {
+        let mut rng = DeterministicRng(seed);
+        let matrix = cell.matrix();
+
+        let mut system = SimpleSystem::new(cell);
+        for _ in 0..natoms {
+            let fractional = [rng.next_f64(), rng.next_f64(), rng.next_f64()];
+            system.add_atom(0, fractional_to_cartesian(fractional, matrix));
+        }
+        return system;
+    }
+
+    fn sorted_pairs(mut pairs: Vec<Pair>) -> Vec<Pair> {
+        pairs.sort_by_key(|pair| (pair.first, pair.second, pair.cell_shift));
+        return pairs;
+    }
+
+    /// Check that `linked_cell_list` and `brute_force_pairs` agree on the
+    /// exact same set of pairs (same pair of centers, same periodic shift,
+    /// same distance and vector) for the given `cell`/`natoms`/`cutoff`.
+    #[track_caller]
+    fn check_linked_cell_list_matches_brute_force(cell: UnitCell, natoms: usize, cutoff: f64, seed: u64) {
+        let system = random_system(cell, natoms, seed);
+
+        let expected = sorted_pairs(brute_force_pairs(&system, cutoff));
+        let actual = sorted_pairs(
+            linked_cell_list(&system, cutoff)
+                .expect("these parameters should be eligible for the linked cell list")
+        );
+
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(&expected) {
+            assert_eq!(actual.first, expected.first);
+            assert_eq!(actual.second, expected.second);
+            assert_eq!(actual.cell_shift, expected.cell_shift);
+            assert!((actual.distance - expected.distance).abs() < 1e-9);
+            for k in 0..3 {
+                assert!((actual.vector[k] - expected.vector[k]).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn linked_cell_list_matches_brute_force_cubic() {
+        // atom count comfortably above `MIN_ATOMS_FOR_CELL_LIST`
+        check_linked_cell_list_matches_brute_force(UnitCell::cubic(12.0), 200, 2.0, 0xdead_beef);
+        // atom count right at the threshold where the cell list kicks in
+        check_linked_cell_list_matches_brute_force(UnitCell::cubic(12.0), MIN_ATOMS_FOR_CELL_LIST, 2.0, 0xc0ff_ee);
+        // cutoff close to the `MIN_CELLS_PER_DIRECTION` boundary
+        check_linked_cell_list_matches_brute_force(UnitCell::cubic(12.0), 200, 3.9, 0x1234_5678);
+    }
+
+    #[test]
+    fn linked_cell_list_matches_brute_force_skewed() {
+        // a skewed (triclinic) cell, to exercise the non-orthogonal wrapping
+        // of `linked_cell_list`
+        let skewed = UnitCell::triclinic(
+            Vector3D::new(12.0, 0.0, 0.0),
+            Vector3D::new(3.0, 11.0, 0.0),
+            Vector3D::new(2.0, 1.5, 10.0),
+        );
+        check_linked_cell_list_matches_brute_force(skewed, 200, 2.0, 0x0bad_f00d);
+    }
+
+    #[test]
+    fn self_pairs_see_both_periodic_images() {
+        // for a self pair, what matters is the cutoff against the *full*
+        // cell length (not just half of it, as for distinct-atom pairs):
+        // the closest nonzero self-translation is a full lattice vector
+        // away, so the cutoff must exceed the cell length itself for a
+        // single atom to pair with its own periodic images
+        let mut system = SimpleSystem::new(UnitCell::cubic(4.0));
+        system.add_atom(0, Vector3D::new(0.0, 0.0, 0.0));
+
+        system.compute_neighbors(4.5);
+
+        let self_pairs = system.pairs_containing(0);
+        let mut shifts: Vec<[i32; 3]> = self_pairs.iter().map(|pair| pair.cell_shift).collect();
+        shifts.sort();
+
+        let mut unique_shifts = shifts.clone();
+        unique_shifts.dedup();
+        assert_eq!(shifts.len(), unique_shifts.len(), "no periodic image should be counted twice");
+
+        // both directions along x (and equivalently y and z) must be present
+        assert!(shifts.contains(&[1, 0, 0]));
+        assert!(shifts.contains(&[-1, 0, 0]));
+        assert!(shifts.contains(&[0, 1, 0]));
+        assert!(shifts.contains(&[0, -1, 0]));
+        assert!(shifts.contains(&[0, 0, 1]));
+        assert!(shifts.contains(&[0, 0, -1]));
+    }
+
+    #[test]
+    fn linked_cell_list_bails_out_below_min_atoms() {
+        // below `MIN_ATOMS_FOR_CELL_LIST`, building the grid is not worth it
+        // and callers should fall back to `brute_force_pairs`
+        let system = random_system(UnitCell::cubic(12.0), MIN_ATOMS_FOR_CELL_LIST - 1, 0x42);
+        assert!(linked_cell_list(&system, 2.0).is_none());
+    }
 }